@@ -0,0 +1,112 @@
+//! Ancillary PNG chunks: text metadata, gamma, physical pixel dimensions,
+//! and anything else `lodepng` decoded but didn't interpret.
+
+use lodepng;
+
+/// The physical size of one pixel, as stored in a `pHYs` chunk.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Physical {
+    /// Pixels per unit, horizontally.
+    pub x: u32,
+    /// Pixels per unit, vertically.
+    pub y: u32,
+    /// Whether `x`/`y` are a ratio in meters, rather than an unspecified
+    /// aspect ratio.
+    pub meters: bool
+}
+
+/// A chunk `lodepng` doesn't have first-class support for, identified by
+/// its four-byte type (e.g. `b"sRGB"`).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct UnknownChunk {
+    /// The chunk's four-character type.
+    pub kind: [u8; 4],
+    /// The chunk's raw, un-decoded payload.
+    pub data: Vec<u8>
+}
+
+/// Ancillary chunks read from (or to be written to) a PNG, alongside its
+/// pixel data.
+///
+/// A default `Chunks` carries nothing: no text, no gamma, no physical
+/// dimensions, no unknown chunks. [`Png::decode`](crate::Png::decode) and
+/// friends populate one from the file; set [`Png::chunks_mut`](crate::Png::chunks_mut)
+/// before [`Png::encode`](crate::Png::encode) to have it written back out.
+#[derive(Clone, Debug, Default)]
+pub struct Chunks {
+    /// `tEXt`/`zTXt` key-value metadata, such as authorship or the
+    /// parameters used to generate the image. International text (`iTXt`)
+    /// chunks are neither read into nor written from this list.
+    pub text: Vec<(String, String)>,
+    /// The `gAMA` chunk's gamma value, scaled by 100000 (lodepng's own
+    /// convention), if present.
+    pub gamma: Option<u32>,
+    /// The `pHYs` chunk's physical pixel dimensions, if present.
+    pub physical: Option<Physical>,
+    /// Any other chunk `lodepng` decoded but didn't interpret.
+    pub unknown: Vec<UnknownChunk>
+}
+
+impl Chunks {
+    /// An empty set of chunks.
+    pub fn new() -> Self {
+        Chunks::default()
+    }
+
+    /// Reads whatever ancillary chunks `lodepng` collected while decoding.
+    pub(crate) fn read(info: &lodepng::Info) -> Self {
+        let text = info.text_keys()
+            .iter()
+            .zip(info.text_values().iter())
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let gamma = if info.gama_defined {
+            Some(info.gama_gamma)
+        } else {
+            None
+        };
+
+        let physical = if info.phys_defined {
+            Some(Physical {
+                x: info.phys_x,
+                y: info.phys_y,
+                meters: info.phys_unit == 1
+            })
+        } else {
+            None
+        };
+
+        let unknown = info.unknown_chunks()
+            .map(|chunk| UnknownChunk {
+                kind: chunk.kind(),
+                data: chunk.data().to_vec()
+            })
+            .collect();
+
+        Chunks { text, gamma, physical, unknown }
+    }
+
+    /// Writes these chunks into a `lodepng::Info` that's about to encode.
+    pub(crate) fn write(&self, info: &mut lodepng::Info) {
+        for (key, value) in &self.text {
+            let _ = info.add_text(key, value);
+        }
+
+        if let Some(gamma) = self.gamma {
+            info.gama_defined = true;
+            info.gama_gamma = gamma;
+        }
+
+        if let Some(physical) = self.physical {
+            info.phys_defined = true;
+            info.phys_x = physical.x;
+            info.phys_y = physical.y;
+            info.phys_unit = if physical.meters { 1 } else { 0 };
+        }
+
+        for chunk in &self.unknown {
+            let _ = info.push_unknown_chunk(chunk.kind, &chunk.data);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Pixel color models `Png` can decode and encode natively, without forcing
+//! a lossy round-trip through 8-bit RGBA.
+
+use lodepng::ffi::CVec;
+use std::mem;
+
+use framing::Rgba;
+
+use NativeBuffer;
+
+/// A `lodepng` color type, independent of bit depth.
+///
+/// This mirrors the subset of `lodepng::ColorType` that `png_framing`
+/// understands; combined with a [`BitDepth`] it picks out one of the
+/// [`ColorModel`] pixel types below.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ColorType {
+    /// Grayscale, one channel.
+    Grey,
+    /// RGB, three channels, no alpha.
+    Rgb,
+    /// RGBA, four channels.
+    Rgba
+}
+
+impl From<ColorType> for lodepng::ColorType {
+    fn from(color: ColorType) -> Self {
+        match color {
+            ColorType::Grey => lodepng::ColorType::GREY,
+            ColorType::Rgb => lodepng::ColorType::RGB,
+            ColorType::Rgba => lodepng::ColorType::RGBA
+        }
+    }
+}
+
+/// The number of bits lodepng stores per channel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum BitDepth {
+    /// One byte per channel.
+    Eight,
+    /// Two bytes per channel.
+    Sixteen
+}
+
+impl From<BitDepth> for u32 {
+    fn from(depth: BitDepth) -> Self {
+        match depth {
+            BitDepth::Eight => 8,
+            BitDepth::Sixteen => 16
+        }
+    }
+}
+
+/// Associates a pixel type with the `lodepng` color model it decodes from
+/// and encodes to, so `Png` can work with formats other than 8-bit RGBA.
+///
+/// This is implemented for `framing::Rgba` (the crate's long-standing
+/// default) as well as [`Gray`], [`Rgb`], [`Gray16`], and [`Rgba16`].
+pub trait ColorModel: Copy {
+    /// The number of bytes one pixel occupies in a native buffer.
+    const BYTES: usize;
+
+    /// The `ColorType` this pixel corresponds to.
+    fn color_type() -> ColorType;
+
+    /// The `BitDepth` this pixel corresponds to.
+    fn bit_depth() -> BitDepth;
+
+    /// Reads one pixel out of a byte buffer at the given pointer.
+    ///
+    /// # Safety
+    /// `bytes` must have at least `Self::BYTES` readable bytes at its
+    /// location.
+    unsafe fn read(bytes: *const u8) -> Self;
+
+    /// Writes one pixel into a byte buffer at the given pointer.
+    ///
+    /// # Safety
+    /// `bytes` must have at least `Self::BYTES` writable bytes at its
+    /// location.
+    unsafe fn write(self, bytes: *mut u8);
+
+    /// Pulls the matching variant out of a decoded `lodepng::Image`.
+    ///
+    /// Returns `None` if the file didn't actually decode to this color
+    /// model, which can happen if lodepng fell back to a different one
+    /// than was requested.
+    #[doc(hidden)]
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)>;
+}
+
+/// Reinterprets a `CVec<A>` as a `CVec<B>` of the same byte size.
+///
+/// Both `A` and `B` are plain, packed pixel structs of matching layout
+/// (e.g. `lodepng::RGBA<u8>` and `framing::Rgba`), so this is
+/// just a pointer/length reinterpretation, not an actual conversion.
+unsafe fn retype_cvec<A, B>(buffer: CVec<A>) -> CVec<B> {
+    assert_eq!(mem::size_of::<A>(), mem::size_of::<B>());
+    mem::transmute(buffer)
+}
+
+impl ColorModel for Rgba {
+    const BYTES: usize = 4;
+
+    fn color_type() -> ColorType { ColorType::Rgba }
+    fn bit_depth() -> BitDepth { BitDepth::Eight }
+
+    unsafe fn read(bytes: *const u8) -> Self {
+        Rgba(*bytes, *bytes.offset(1), *bytes.offset(2), *bytes.offset(3))
+    }
+
+    unsafe fn write(self, bytes: *mut u8) {
+        *bytes = self.0;
+        *bytes.offset(1) = self.1;
+        *bytes.offset(2) = self.2;
+        *bytes.offset(3) = self.3;
+    }
+
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)> {
+        match image {
+            lodepng::Image::RGBA(bmp) => Some((
+                bmp.width,
+                bmp.height,
+                NativeBuffer::new(unsafe { retype_cvec(bmp.buffer) })
+            )),
+            _ => None
+        }
+    }
+}
+
+/// A single-channel 8-bit grayscale pixel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Gray(pub u8);
+
+impl ColorModel for Gray {
+    const BYTES: usize = 1;
+
+    fn color_type() -> ColorType { ColorType::Grey }
+    fn bit_depth() -> BitDepth { BitDepth::Eight }
+
+    unsafe fn read(bytes: *const u8) -> Self { Gray(*bytes) }
+    unsafe fn write(self, bytes: *mut u8) { *bytes = self.0; }
+
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)> {
+        match image {
+            lodepng::Image::Grey(bmp) => Some((
+                bmp.width,
+                bmp.height,
+                NativeBuffer::new(unsafe { retype_cvec(bmp.buffer) })
+            )),
+            _ => None
+        }
+    }
+}
+
+/// A three-channel 8-bit RGB pixel, with no alpha channel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl ColorModel for Rgb {
+    const BYTES: usize = 3;
+
+    fn color_type() -> ColorType { ColorType::Rgb }
+    fn bit_depth() -> BitDepth { BitDepth::Eight }
+
+    unsafe fn read(bytes: *const u8) -> Self {
+        Rgb(*bytes, *bytes.offset(1), *bytes.offset(2))
+    }
+
+    unsafe fn write(self, bytes: *mut u8) {
+        *bytes = self.0;
+        *bytes.offset(1) = self.1;
+        *bytes.offset(2) = self.2;
+    }
+
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)> {
+        match image {
+            lodepng::Image::RGB(bmp) => Some((
+                bmp.width,
+                bmp.height,
+                NativeBuffer::new(unsafe { retype_cvec(bmp.buffer) })
+            )),
+            _ => None
+        }
+    }
+}
+
+/// A single-channel 16-bit-per-channel grayscale pixel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Gray16(pub u16);
+
+impl ColorModel for Gray16 {
+    const BYTES: usize = 2;
+
+    fn color_type() -> ColorType { ColorType::Grey }
+    fn bit_depth() -> BitDepth { BitDepth::Sixteen }
+
+    // lodepng packs 16-bit samples big-endian (per the PNG spec), regardless
+    // of host endianness, so the in-memory byte order has to be swapped on a
+    // little-endian host rather than read/written as a native `u16`.
+    unsafe fn read(bytes: *const u8) -> Self {
+        Gray16(u16::from_be((bytes as *const u16).read_unaligned()))
+    }
+
+    unsafe fn write(self, bytes: *mut u8) {
+        (bytes as *mut u16).write_unaligned(self.0.to_be())
+    }
+
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)> {
+        match image {
+            lodepng::Image::Grey16(bmp) => Some((
+                bmp.width,
+                bmp.height,
+                NativeBuffer::new(unsafe { retype_cvec(bmp.buffer) })
+            )),
+            _ => None
+        }
+    }
+}
+
+/// A four-channel 16-bit-per-channel RGBA pixel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Rgba16(pub u16, pub u16, pub u16, pub u16);
+
+impl ColorModel for Rgba16 {
+    const BYTES: usize = 8;
+
+    fn color_type() -> ColorType { ColorType::Rgba }
+    fn bit_depth() -> BitDepth { BitDepth::Sixteen }
+
+    // See the comment on `Gray16::read`: lodepng's 16-bit samples are
+    // big-endian, so each channel has to be byte-swapped on a little-endian
+    // host.
+    unsafe fn read(bytes: *const u8) -> Self {
+        let channel = |n: isize| u16::from_be((bytes as *const u16).offset(n).read_unaligned());
+        Rgba16(channel(0), channel(1), channel(2), channel(3))
+    }
+
+    unsafe fn write(self, bytes: *mut u8) {
+        let channels = [self.0, self.1, self.2, self.3];
+        for (n, channel) in channels.iter().enumerate() {
+            (bytes as *mut u16).offset(n as isize).write_unaligned(channel.to_be());
+        }
+    }
+
+    fn from_image(image: lodepng::Image) -> Option<(usize, usize, NativeBuffer<Self>)> {
+        match image {
+            lodepng::Image::RGBA16(bmp) => Some((
+                bmp.width,
+                bmp.height,
+                NativeBuffer::new(unsafe { retype_cvec(bmp.buffer) })
+            )),
+            _ => None
+        }
+    }
+}
+
+#[test]
+fn sixteen_bit_channels_are_big_endian() {
+    // A known-good two-byte sample: lodepng (and the PNG spec it follows)
+    // stores 16-bit channels MSB-first, so these bytes are the value
+    // 0x0102, not 0x0201.
+    let bytes = [0x01u8, 0x02u8];
+    assert_eq!(unsafe { Gray16::read(bytes.as_ptr()) }, Gray16(0x0102));
+
+    let mut roundtrip = [0u8; 2];
+    unsafe { Gray16(0x0102).write(roundtrip.as_mut_ptr()) };
+    assert_eq!(roundtrip, bytes);
+
+    let rgba16_bytes = [0x01u8, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+    assert_eq!(
+        unsafe { Rgba16::read(rgba16_bytes.as_ptr()) },
+        Rgba16(0x0102, 0x0304, 0x0506, 0x0708)
+    );
+
+    let mut rgba16_roundtrip = [0u8; 8];
+    unsafe { Rgba16(0x0102, 0x0304, 0x0506, 0x0708).write(rgba16_roundtrip.as_mut_ptr()) };
+    assert_eq!(rgba16_roundtrip, rgba16_bytes);
+}
@@ -0,0 +1,119 @@
+//! Encoder configuration: row filter strategy and deflate compression level.
+//!
+//! Every `Png::encode`/`Png::save` call used to go through lodepng's
+//! defaults with no way to trade size for speed. [`EncodeOptions`] exposes
+//! the two knobs that matter most for large renders: which [`Filter`] each
+//! scanline gets, and how hard deflate tries to shrink the result.
+
+use lodepng;
+
+/// A PNG row filter, applied independently to each scanline before
+/// compression.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Filter {
+    /// No filtering; each byte is stored as-is.
+    None,
+    /// Each byte minus the byte to its left.
+    Sub,
+    /// Each byte minus the byte directly above it.
+    Up,
+    /// Each byte minus the average of the byte to its left and the byte
+    /// above it.
+    Average,
+    /// Each byte minus the Paeth predictor of the bytes to its left,
+    /// above, and above-left: `p = a + b - c`, where `a` is the byte to the
+    /// left, `b` is the byte above, and `c` is the byte above-left; the
+    /// predictor is whichever of `a`, `b`, `c` lies closest to `p` (ties
+    /// favor `a`, then `b`).
+    Paeth
+}
+
+impl From<Filter> for u8 {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::None => 0,
+            Filter::Sub => 1,
+            Filter::Up => 2,
+            Filter::Average => 3,
+            Filter::Paeth => 4
+        }
+    }
+}
+
+/// How the encoder picks a [`Filter`] for each scanline.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum FilterStrategy {
+    /// Use the same filter for every scanline.
+    Fixed(Filter),
+    /// For each scanline, try every filter and keep whichever produces the
+    /// smallest sum of absolute values, treating the filtered bytes as
+    /// signed. This is the standard "minimum sum of absolute differences"
+    /// heuristic for shrinking PNGs of large, detailed renders, and is what
+    /// `lodepng` calls `MINSUM`.
+    Adaptive
+}
+
+impl Default for FilterStrategy {
+    fn default() -> Self { FilterStrategy::Adaptive }
+}
+
+/// Builder for the settings [`Png::encode_with`](crate::Png::encode_with)
+/// and [`Png::save_with`](crate::Png::save_with) pass to `lodepng`.
+///
+/// ```rust
+/// use png_framing::{EncodeOptions, Filter, FilterStrategy};
+///
+/// // Favor encode speed over file size.
+/// let fast = EncodeOptions::new()
+///     .filter_strategy(FilterStrategy::Fixed(Filter::Up))
+///     .compression_level(1);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct EncodeOptions {
+    filter_strategy: FilterStrategy,
+    compression_level: u8
+}
+
+impl EncodeOptions {
+    /// Starts from lodepng's own defaults: adaptive filtering, compression
+    /// level 6.
+    pub fn new() -> Self {
+        EncodeOptions {
+            filter_strategy: FilterStrategy::default(),
+            compression_level: 6
+        }
+    }
+
+    /// Sets the row filter strategy.
+    pub fn filter_strategy(mut self, strategy: FilterStrategy) -> Self {
+        self.filter_strategy = strategy;
+        self
+    }
+
+    /// Sets the deflate compression level, from `0` (store, fastest) to
+    /// `9` (smallest, slowest).
+    pub fn compression_level(mut self, level: u8) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// Applies these options to a `lodepng::State` that's about to encode
+    /// an image of the given height.
+    pub(crate) fn apply(&self, state: &mut lodepng::State, height: usize) {
+        match self.filter_strategy {
+            FilterStrategy::Fixed(filter) => {
+                state.encoder.filter_strategy = lodepng::FilterStrategy::PREDEFINED;
+                state.encoder.predefined_filters = vec![filter.into(); height];
+            },
+            FilterStrategy::Adaptive => {
+                state.encoder.filter_strategy = lodepng::FilterStrategy::MINSUM;
+            }
+        }
+
+        state.encoder.zlibsettings.set_level(self.compression_level);
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self { EncodeOptions::new() }
+}
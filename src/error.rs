@@ -0,0 +1,88 @@
+//! A structured error type, so callers can tell an I/O failure from a
+//! malformed-PNG failure from a dimension mismatch instead of getting back
+//! a single opaque variant.
+
+use std::fmt;
+use std::io;
+
+use lodepng;
+
+/// Everything that can go wrong decoding, encoding, loading, or saving a
+/// PNG.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing the underlying file or stream failed.
+    Io(io::Error),
+    /// `lodepng` rejected the bytes as a malformed PNG while decoding.
+    Decode {
+        /// `lodepng`'s own numeric error code.
+        lodepng_code: u32,
+        /// `lodepng`'s message for that code.
+        message: String
+    },
+    /// `lodepng` failed while encoding, which usually means the pixel
+    /// buffer didn't match its declared width, height, or color model.
+    Encode {
+        /// `lodepng`'s own numeric error code.
+        lodepng_code: u32,
+        /// `lodepng`'s message for that code.
+        message: String
+    },
+    /// A pixel buffer's length didn't match what its width, height, and
+    /// color model implied.
+    DimensionMismatch {
+        /// The buffer length the dimensions and color model implied.
+        expected: usize,
+        /// The buffer length actually found.
+        actual: usize
+    }
+}
+
+impl Error {
+    pub(crate) fn decode(err: lodepng::Error) -> Self {
+        Error::Decode { lodepng_code: err.code(), message: err.to_string() }
+    }
+
+    pub(crate) fn encode(err: lodepng::Error) -> Self {
+        Error::Encode { lodepng_code: err.code(), message: err.to_string() }
+    }
+
+    /// `lodepng` decoded successfully, but into a different color model
+    /// than the one we asked for and locked `info_raw` to. This shouldn't
+    /// normally happen, but isn't a panic-worthy invariant violation either.
+    pub(crate) fn color_mismatch() -> Self {
+        Error::Decode {
+            lodepng_code: 0,
+            message: "lodepng returned an unexpected color model".into()
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::Decode { lodepng_code, ref message } =>
+                write!(f, "failed to decode PNG (lodepng error {}): {}", lodepng_code, message),
+            Error::Encode { lodepng_code, ref message } =>
+                write!(f, "failed to encode PNG (lodepng error {}): {}", lodepng_code, message),
+            Error::DimensionMismatch { expected, actual } =>
+                write!(f, "expected a buffer of {} bytes, but got {}", expected, actual)
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::Decode { .. } | Error::Encode { .. } | Error::DimensionMismatch { .. } => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -9,7 +9,7 @@
 //! use png_framing::Png;
 //!
 //! // A tiny image.
-//! let bytes = vec![255, 0, 0, 255, 0, 0, 255, 255]; 
+//! let bytes = vec![255, 0, 0, 255, 0, 0, 255, 255];
 //! let (width, height) = (2, 1);
 //!
 //! // Save it!
@@ -19,51 +19,201 @@
 extern crate lodepng;
 extern crate framing;
 
+mod color;
+mod encoder;
+mod chunks;
+mod stream;
+mod error;
+
+pub use color::{ColorType, BitDepth, ColorModel, Gray, Rgb, Gray16, Rgba16};
+pub use encoder::{EncodeOptions, Filter, FilterStrategy};
+pub use chunks::{Chunks, Physical, UnknownChunk};
+pub use error::Error;
+
 use framing::{Image, Rgba, Chunky};
 use lodepng::ffi::CVec;
-use std::{mem, ptr, slice};
+use std::{mem, slice};
+use std::marker::PhantomData;
 use std::path::Path;
 
-/// A raw RGBA image that can be converted easily to/from a PNG.
-pub struct Png<T> {
+/// A raw image that can be converted easily to/from a PNG.
+///
+/// `P` is the pixel's color model (see [`ColorModel`]) and defaults to
+/// `framing::Rgba`, the crate's original 8-bit RGBA behavior.
+pub struct Png<T, P = Rgba> {
     width: usize,
     height: usize,
-    buffer: T
+    buffer: T,
+    chunks: Chunks,
+    pixel: PhantomData<P>
 }
 
 impl Png<Native> {
     /// Decodes the image which has been encoded in the given bytes.
     pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
-        match lodepng::decode32(bytes) {
-            Ok(bmp) => {
-                assert_eq!(bmp.buffer.len(), bmp.width * bmp.height);
+        let mut state = lodepng::State::new();
+        state.decoder.remember_unknown_chunks = true;
+
+        match state.decode(bytes) {
+            Ok(lodepng::Image::RGBA(bmp)) => {
+                let pixel_bytes = mem::size_of::<lodepng::RGBA<u8>>();
+                let expected = bmp.width * bmp.height * pixel_bytes;
+                let actual = bmp.buffer.len() * pixel_bytes;
+
+                if actual != expected {
+                    return Err(Error::DimensionMismatch { expected, actual });
+                }
+
                 Ok(Png {
                     width: bmp.width,
                     height: bmp.height,
-                    buffer: Native::new(bmp.buffer)
+                    buffer: NativeBuffer::new(bmp.buffer),
+                    chunks: Chunks::read(state.info_png()),
+                    pixel: PhantomData
                 })
             },
-            Err(_) => Err(Error)
+            Ok(_) => Err(Error::color_mismatch()),
+            Err(err) => Err(Error::decode(err))
         }
     }
 
     /// Loads the PNG at the given file path.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        match lodepng::decode32_file(path) {
-            Ok(bmp) => {
-                assert_eq!(bmp.buffer.len(), bmp.width * bmp.height);
+        let mut state = lodepng::State::new();
+        state.decoder.remember_unknown_chunks = true;
+
+        let bytes = std::fs::read(path)?;
+
+        match state.decode(&bytes) {
+            Ok(lodepng::Image::RGBA(bmp)) => {
+                let pixel_bytes = mem::size_of::<lodepng::RGBA<u8>>();
+                let expected = bmp.width * bmp.height * pixel_bytes;
+                let actual = bmp.buffer.len() * pixel_bytes;
+
+                if actual != expected {
+                    return Err(Error::DimensionMismatch { expected, actual });
+                }
+
                 Ok(Png {
                     width: bmp.width,
                     height: bmp.height,
-                    buffer: Native::new(bmp.buffer)
+                    buffer: NativeBuffer::new(bmp.buffer),
+                    chunks: Chunks::read(state.info_png()),
+                    pixel: PhantomData
                 })
             },
-            Err(_) => Err(Error)
+            Ok(_) => Err(Error::color_mismatch()),
+            Err(err) => Err(Error::decode(err))
         }
     }
+
+    /// Decodes the image, recovering what it can instead of discarding
+    /// everything on a truncated or corrupt file.
+    ///
+    /// This decodes with lodepng's checksum checks turned off (chunk CRCs
+    /// and the zlib Adler-32), so a file whose only fault is a bad checksum
+    /// still comes back whole as `Recovery::Complete`. No other corruption
+    /// (such as a missing or garbled `IEND`) is tolerated. Only once the
+    /// header tells us the image's dimensions but the pixel data itself
+    /// couldn't be decoded (e.g. the file is truncated mid-stream) does
+    /// this fall back to a blank, transparent-black image of the right
+    /// size, so a caller at least gets correctly-sized placeholder pixels
+    /// instead of nothing.
+    ///
+    /// lodepng's decoder is all-or-nothing at the pixel level: there's no
+    /// supported way through this crate's bindings to recover the
+    /// scanlines that *did* decode before a mid-stream truncation, so this
+    /// can't report a partial scanline count the way a byte-for-byte
+    /// streaming decoder could.
+    ///
+    /// Returns `Err` only if even the dimensions couldn't be read (e.g. the
+    /// file doesn't start with a valid PNG signature at all).
+    pub fn decode_lossy(bytes: &[u8]) -> Result<(Self, Recovery), Error> {
+        let mut state = lodepng::State::new();
+        state.decoder.remember_unknown_chunks = true;
+        state.decoder.ignore_crc = true;
+        state.decoder.zlibsettings.ignore_adler32 = true;
+
+        if let Ok(lodepng::Image::RGBA(bmp)) = state.decode(bytes) {
+            if bmp.buffer.len() == bmp.width * bmp.height {
+                let height = bmp.height;
+
+                return Ok((Png {
+                    width: bmp.width,
+                    height,
+                    buffer: NativeBuffer::new(bmp.buffer),
+                    chunks: Chunks::read(state.info_png()),
+                    pixel: PhantomData
+                }, Recovery::Complete));
+            }
+        }
+
+        let (width, height) = state.inspect(bytes).map_err(Error::decode)?;
+        let blank = vec![lodepng::RGBA { r: 0, g: 0, b: 0, a: 0 }; width * height];
+
+        Ok((Png {
+            width,
+            height,
+            buffer: NativeBuffer::new(CVec::new(blank)),
+            chunks: Chunks::read(state.info_png()),
+            pixel: PhantomData
+        }, Recovery::Blank { of: height }))
+    }
+
+    /// Like [`Png::load`], but falls back to [`Png::decode_lossy`]'s
+    /// recovery strategy for a truncated or corrupt file.
+    pub fn load_lossy<P: AsRef<Path>>(path: P) -> Result<(Self, Recovery), Error> {
+        let bytes = std::fs::read(path)?;
+        Self::decode_lossy(&bytes)
+    }
+}
+
+/// How much of a [`Png::decode_lossy`]/[`Png::load_lossy`] result was
+/// actually recovered from the source bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Recovery {
+    /// The pixel data decoded successfully (possibly only after tolerating
+    /// a bad checksum or similar non-fatal corruption).
+    Complete,
+    /// The pixel data itself couldn't be decoded; the buffer is `of`
+    /// scanlines of transparent black, sized to the image's real
+    /// dimensions but carrying none of its actual pixels.
+    Blank {
+        /// The number of scanlines the image should have.
+        of: usize
+    }
 }
 
-impl<T> Png<T> {
+impl<P: ColorModel> Png<NativeBuffer<P>, P> {
+    /// Decodes the image in its native color model, `P`, instead of always
+    /// converting through 8-bit RGBA.
+    ///
+    /// `P` determines both the `ColorType` and `BitDepth` passed to
+    /// `lodepng`; if the file doesn't actually decode to that color model,
+    /// this returns `Err`.
+    pub fn decode_as(bytes: &[u8]) -> Result<Self, Error> {
+        let mut state = lodepng::State::new();
+        state.decoder.remember_unknown_chunks = true;
+        state.info_raw_mut().colortype = P::color_type().into();
+        state.info_raw_mut().set_bitdepth(P::bit_depth().into());
+
+        match state.decode(bytes) {
+            Ok(image) => match P::from_image(image) {
+                Some((width, height, buffer)) => Ok(Png {
+                    width,
+                    height,
+                    buffer,
+                    chunks: Chunks::read(state.info_png()),
+                    pixel: PhantomData
+                }),
+                None => Err(Error::color_mismatch())
+            },
+            Err(err) => Err(Error::decode(err))
+        }
+    }
+}
+
+impl<T, P> Png<T, P> {
     /// Borrows the buffer that the PNG was created with.
     pub fn buffer(&self) -> &T {
         &self.buffer
@@ -76,10 +226,31 @@ impl<T> Png<T> {
     pub fn into_buffer(self) -> T {
         self.buffer
     }
+
+    /// Borrows the ancillary chunks (text metadata, gamma, physical
+    /// dimensions, etc.) read from or queued to be written with this PNG.
+    pub fn chunks(&self) -> &Chunks {
+        &self.chunks
+    }
+
+    /// Mutably borrows the ancillary chunks, so they can be edited before
+    /// [`Png::encode`] or [`Png::save`] writes them out.
+    pub fn chunks_mut(&mut self) -> &mut Chunks {
+        &mut self.chunks
+    }
+
+    /// Replaces the ancillary chunks wholesale.
+    pub fn set_chunks(&mut self, chunks: Chunks) {
+        self.chunks = chunks;
+    }
 }
 
-impl<T> Png<T> where T: AsRef<[u8]> {
-    /// Creates a new PNG given the width, height, and raw RGBA image data.
+impl<T: AsRef<[u8]>> Png<T, Rgba> {
+    /// Creates a new PNG given the width, height, and raw RGBA pixel data.
+    ///
+    /// This fixes the pixel type to [`Rgba`], the crate's default, so it
+    /// infers from just `(width, height, buffer)` with no turbofish needed.
+    /// For any other [`ColorModel`], use [`Png::from_bytes_as`].
     ///
     /// # Panics
     ///
@@ -88,9 +259,30 @@ impl<T> Png<T> where T: AsRef<[u8]> {
         width: usize,
         height: usize,
         buffer: T
-    ) -> Png<T> {
-        assert_eq!(width * height * 4, buffer.as_ref().len());
-        Png { width, height, buffer }
+    ) -> Png<T, Rgba> {
+        Png::from_bytes_as(width, height, buffer)
+    }
+}
+
+impl<T, P> Png<T, P> where T: AsRef<[u8]>, P: ColorModel {
+    /// Creates a new PNG given the width, height, and raw pixel data,
+    /// encoded through the `P` color model.
+    ///
+    /// Unlike [`Png::from_bytes`], `P` isn't pinned to [`Rgba`], so it must
+    /// be inferred from context (e.g. an explicit turbofish, or the place
+    /// the result is used).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer's length is not exactly
+    /// `width * height * P::BYTES`.
+    pub fn from_bytes_as(
+        width: usize,
+        height: usize,
+        buffer: T
+    ) -> Png<T, P> {
+        assert_eq!(width * height * P::BYTES, buffer.as_ref().len());
+        Png { width, height, buffer, chunks: Chunks::new(), pixel: PhantomData }
     }
 
     /// Saves the PNG to the given file path.
@@ -98,18 +290,8 @@ impl<T> Png<T> where T: AsRef<[u8]> {
     /// **Any existing file at the path will be overwritten.** This is basically
     /// the same as encoding the image and writing it yourself, but is a lot
     /// more convenient.
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        let result = lodepng::encode32_file(
-            path,
-            self.buffer.as_ref(),
-            self.width,
-            self.height
-        );
-
-        match result {
-            Ok(_) => Ok(()),
-            Err(_) => Err(Error)
-        }
+    pub fn save<Q: AsRef<Path>>(&self, path: Q) -> Result<(), Error> {
+        self.save_with(path, &EncodeOptions::default())
     }
 
     /// Encodes the PNG, allocating the necessary memory for the encoded data.
@@ -117,15 +299,38 @@ impl<T> Png<T> where T: AsRef<[u8]> {
     /// The output is an array of bytes with the compressed PNG data, suitable
     /// for sending over a network or writing to a file.
     pub fn encode(&self) -> Result<CVec<u8>, Error> {
-        let result = lodepng::encode32(
-            self.buffer.as_ref(),
-            self.width,
-            self.height
-        );
+        self.encode_with(&EncodeOptions::default())
+    }
 
-        match result {
+    /// Saves the PNG to the given file path using custom encoder settings.
+    ///
+    /// **Any existing file at the path will be overwritten.**
+    pub fn save_with<Q: AsRef<Path>>(&self, path: Q, options: &EncodeOptions) -> Result<(), Error> {
+        let encoded = self.encode_with(options)?;
+        std::fs::write(path, encoded.as_ref())?;
+        Ok(())
+    }
+
+    /// Encodes the PNG using custom encoder settings, such as a specific
+    /// [`Filter`] strategy or deflate compression level. See
+    /// [`EncodeOptions`].
+    ///
+    /// The chunks in [`Png::chunks`] are written out alongside the pixel
+    /// data.
+    pub fn encode_with(&self, options: &EncodeOptions) -> Result<CVec<u8>, Error> {
+        let mut state = lodepng::State::new();
+
+        state.info_raw_mut().colortype = P::color_type().into();
+        state.info_raw_mut().set_bitdepth(P::bit_depth().into());
+        state.info_png_mut().color.colortype = P::color_type().into();
+        state.info_png_mut().color.set_bitdepth(P::bit_depth().into());
+
+        self.chunks.write(state.info_png_mut());
+        options.apply(&mut state, self.height);
+
+        match state.encode(self.buffer.as_ref(), self.width, self.height) {
             Ok(vec) => Ok(vec),
-            Err(_) => Err(Error)
+            Err(err) => Err(Error::encode(err))
         }
     }
 }
@@ -143,63 +348,83 @@ impl Png<Vec<u8>> {
     }
 }
 
+impl<P: ColorModel> Png<Vec<u8>, P> {
+    /// Creates a new image from the given frame, encoding each pixel
+    /// through the `P` color model instead of always going through RGBA.
+    ///
+    /// Unlike [`Png::new`], this visits pixels one at a time rather than
+    /// taking `framing`'s bulk `Chunky` conversion path, since `P` may be
+    /// grayscale or 16-bit.
+    pub fn encode_as<T>(frame: T) -> Self
+    where T: Image + Sync, T::Pixel: Into<P> {
+        let width = frame.width();
+        let height = frame.height();
+        let mut buffer = vec![0u8; width * height * P::BYTES];
+
+        for y in 0..height {
+            for x in 0..width {
+                unsafe {
+                    let pixel: P = frame.pixel(x, y).into();
+                    let offset = P::BYTES * (y * width + x);
+                    pixel.write(buffer.as_mut_ptr().add(offset));
+                }
+            }
+        }
+
+        Png { width, height, buffer, chunks: Chunks::new(), pixel: PhantomData }
+    }
+}
+
 impl From<Chunky<Rgba>> for Png<Vec<u8>> {
     fn from(frame: Chunky<Rgba>) -> Self {
         Png {
             width: frame.width(),
             height: frame.height(),
-            buffer: frame.into_bytes()
+            buffer: frame.into_bytes(),
+            chunks: Chunks::new(),
+            pixel: PhantomData
         }
     }
 }
 
-impl<T> AsRef<[u8]> for Png<T> where T: AsRef<[u8]> {
+impl<T, P> AsRef<[u8]> for Png<T, P> where T: AsRef<[u8]> {
     fn as_ref(&self) -> &[u8] {
         self.buffer.as_ref()
     }
 }
 
-impl<T> AsMut<[u8]> for Png<T> where T: AsMut<[u8]> {
+impl<T, P> AsMut<[u8]> for Png<T, P> where T: AsMut<[u8]> {
     fn as_mut(&mut self) -> &mut [u8] {
         self.buffer.as_mut()
     }
 }
 
-impl<T> Image for Png<T> where T: AsRef<[u8]> {
-    type Pixel = Rgba;
+impl<T, P> Image for Png<T, P> where T: AsRef<[u8]>, P: ColorModel {
+    type Pixel = P;
 
     fn width(&self) -> usize { self.width }
     fn height(&self) -> usize { self.height }
 
     unsafe fn pixel(&self, x: usize, y: usize) -> Self::Pixel {
-        let mut bytes: [u8; 4] = mem::uninitialized();
-        let offset = 4 * (y * self.width + x) as isize;
-
-        ptr::copy_nonoverlapping(
-            self.buffer.as_ref().as_ptr().offset(offset),
-            bytes.as_mut_ptr(),
-            4
-        );
-
-        bytes.into()
+        let offset = P::BYTES * (y * self.width + x);
+        P::read(self.buffer.as_ref().as_ptr().add(offset))
     }
 }
 
 /// A native C pixel array, allocated using malloc.
 ///
-/// You probably won't have to worry about this struct, since it's just an
-/// implementation detail. But if you see a Png<Native>, bear in mind that it
-/// was created by the `lodepng` C library.
-pub struct Native(CVec<lodepng::RGBA<u8>>, usize);
+/// Generic over the lodepng pixel-channel layout, so [`Png::decode_as`] can
+/// return grayscale or 16-bit buffers without copying them into a `Vec<u8>`.
+pub struct NativeBuffer<C>(CVec<C>, usize);
 
-impl Native {
-    fn new(buffer: CVec<lodepng::RGBA<u8>>) -> Self {
-        let length = buffer.len() * mem::size_of::<lodepng::RGBA<u8>>();
-        Native(buffer, length)
+impl<C> NativeBuffer<C> {
+    fn new(buffer: CVec<C>) -> Self {
+        let length = buffer.len() * mem::size_of::<C>();
+        NativeBuffer(buffer, length)
     }
 }
 
-impl AsRef<[u8]> for Native {
+impl<C> AsRef<[u8]> for NativeBuffer<C> {
     fn as_ref(&self) -> &[u8] {
         unsafe {
             slice::from_raw_parts(
@@ -210,13 +435,13 @@ impl AsRef<[u8]> for Native {
     }
 }
 
-/// An unknown error.
+/// A buffer of native 8-bit RGBA pixels, as decoded by [`Png::decode`] and
+/// [`Png::load`].
 ///
-/// Usually the error is obvious, though. For example, when decoding, the error
-/// was probably caused by an invalid PNG. In other cases, the error's source
-/// might be ambiguous, in which case you're out of luck.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
-pub struct Error;
+/// You probably won't have to worry about this type, since it's just an
+/// implementation detail. But if you see a `Png<Native>`, bear in mind that
+/// it was created by the `lodepng` C library.
+pub type Native = NativeBuffer<lodepng::RGBA<u8>>;
 
 #[test]
 fn lossless() {
@@ -0,0 +1,38 @@
+//! Streaming encode/decode: write a PNG straight to any `Write`, or read one
+//! from any `Read`, instead of always going through an owned `Vec`/file
+//! path.
+
+use std::io::{Read, Write};
+
+use {ColorModel, EncodeOptions, Error, Native, Png};
+
+impl<T, P> Png<T, P> where T: AsRef<[u8]>, P: ColorModel {
+    /// Encodes the PNG directly to `w`, rather than always allocating an
+    /// owned buffer first.
+    ///
+    /// `lodepng` still builds one contiguous encoded buffer internally (it
+    /// has no API for producing scanlines incrementally), so this doesn't
+    /// eliminate that allocation. What it avoids is a second, caller-side
+    /// copy of it: the buffer is written to `w` in a single `write_all`
+    /// rather than collected into another `Vec` first.
+    pub fn encode_to<W: Write>(&self, w: W) -> Result<(), Error> {
+        self.encode_to_with(w, &EncodeOptions::default())
+    }
+
+    /// Like [`Png::encode_to`], but with custom [`EncodeOptions`].
+    pub fn encode_to_with<W: Write>(&self, mut w: W, options: &EncodeOptions) -> Result<(), Error> {
+        let encoded = self.encode_with(options)?;
+        w.write_all(encoded.as_ref())?;
+        Ok(())
+    }
+}
+
+impl Png<Native> {
+    /// Decodes a PNG read from `r`, rather than requiring the caller to
+    /// already have the whole file in a byte slice.
+    pub fn decode_from<R: Read>(mut r: R) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        Png::decode(&bytes)
+    }
+}